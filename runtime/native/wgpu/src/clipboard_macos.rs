@@ -0,0 +1,218 @@
+//! macOS Clipboard Backend
+//!
+//! Native NSPasteboard implementation using objc2.
+//! Enabled via `macos-backend` feature flag on macOS.
+//!
+//! # Formats
+//!
+//! MIME types map to system UTIs where one exists (public.utf8-plain-text,
+//! public.html, public.png, public.file-url, ...). Any other MIME type is
+//! written under a custom UTI in the org.qliphoth.mime namespace (see
+//! mime_to_pasteboard_type), so custom formats keep their bytes instead of
+//! degrading to text. File lists become one pasteboard item per file URL,
+//! which is how Finder and other applications exchange them.
+//!
+//! NSPasteboard is synchronous, so like the Wayland backend every operation
+//! completes before returning and results go straight into the event queue.
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use objc2::rc::{autoreleasepool, Retained};
+use objc2::runtime::ProtocolObject;
+use objc2_app_kit::{NSPasteboard, NSPasteboardItem, NSPasteboardWriting};
+use objc2_foundation::{NSArray, NSData, NSString};
+
+use crate::{
+    ClipboardCompletedData, NativeEvent, CLIPBOARD_ERR_EMPTY, CLIPBOARD_ERR_FORMAT_NOT_FOUND,
+    CLIPBOARD_ERR_INTERNAL,
+};
+
+/// Marker type that asks clipboard managers to skip an entry (nspasteboard.org)
+const CONCEALED_TYPE: &str = "org.nspasteboard.ConcealedType";
+
+/// Native macOS pasteboard backend
+pub struct MacClipboardBackend {
+    pasteboard: Retained<NSPasteboard>,
+    /// MIME types written under custom UTIs, for an exact reverse mapping
+    custom_types: HashMap<String, String>,
+}
+
+// NSPasteboard is documented as safe to use from any thread
+unsafe impl Send for MacClipboardBackend {}
+
+impl MacClipboardBackend {
+    /// Create the backend on the general pasteboard
+    pub fn new() -> Result<Self, String> {
+        log::debug!("macOS clipboard backend initialized");
+        Ok(Self {
+            pasteboard: NSPasteboard::generalPasteboard(),
+            custom_types: HashMap::new(),
+        })
+    }
+
+    // =========================================================================
+    // Read Operations
+    // =========================================================================
+
+    /// Read clipboard data in the specified format
+    pub fn read_format(
+        &mut self,
+        mime: &str,
+        callback_id: u64,
+        event_queue: &mut Vec<NativeEvent>,
+        completed: &mut HashMap<u64, ClipboardCompletedData>,
+    ) -> Result<(), i32> {
+        let result = autoreleasepool(|_| self.read(mime));
+
+        match result {
+            Ok(data) => {
+                let data_size = data.len();
+                completed.insert(
+                    callback_id,
+                    ClipboardCompletedData {
+                        data,
+                        formats: None,
+                        format_cstrings: Vec::new(),
+                        image_size: None,
+                        completed_at: Instant::now(),
+                    },
+                );
+                event_queue.push(NativeEvent::ClipboardDataReady { callback_id, data_size });
+            }
+            Err(error_code) => {
+                event_queue.push(NativeEvent::ClipboardError { callback_id, error_code });
+            }
+        }
+        Ok(())
+    }
+
+    /// Get available formats from the pasteboard
+    pub fn get_formats(
+        &mut self,
+        callback_id: u64,
+        event_queue: &mut Vec<NativeEvent>,
+        completed: &mut HashMap<u64, ClipboardCompletedData>,
+    ) -> Result<(), i32> {
+        let types: Vec<String> = autoreleasepool(|_| {
+            self.pasteboard
+                .types()
+                .map(|types| types.iter().map(|t| t.to_string()).collect())
+                .unwrap_or_default()
+        });
+
+        let mut formats: Vec<String> = types.iter().filter_map(|uti| self.type_to_mime(uti)).collect();
+        // Sort for deterministic output order
+        formats.sort();
+        formats.dedup();
+
+        let format_count = formats.len();
+        completed.insert(
+            callback_id,
+            ClipboardCompletedData {
+                data: Vec::new(),
+                formats: Some(formats),
+                format_cstrings: Vec::new(),
+                image_size: None,
+                completed_at: Instant::now(),
+            },
+        );
+        event_queue.push(NativeEvent::ClipboardFormatsAvailable { callback_id, format_count });
+        Ok(())
+    }
+
+    // =========================================================================
+    // Write Operations
+    // =========================================================================
+
+    /// Replace the pasteboard contents with all formats of a write.
+    /// Sensitive writes add the concealed marker type.
+    pub fn write(&mut self, formats: &[(String, Vec<u8>, bool)]) -> Result<(), i32> {
+        autoreleasepool(|_| {
+            let item = NSPasteboardItem::new();
+            let mut items = vec![];
+            let mut written = false;
+
+            for (mime, data, _) in formats {
+                if mime == "text/uri-list" {
+                    // One item per file; the first shares the item with the other formats.
+                    // Re-encoded so hosts can pass unescaped paths
+                    let paths = crate::uri_list_to_paths(&String::from_utf8_lossy(data));
+                    let uris = crate::paths_to_uri_list(&paths);
+                    for (i, url) in uris.lines().enumerate() {
+                        let target = if i == 0 { item.clone() } else { NSPasteboardItem::new() };
+                        target.setString_forType(&NSString::from_str(url), &NSString::from_str("public.file-url"));
+                        if i > 0 {
+                            items.push(target);
+                        }
+                        written = true;
+                    }
+                    continue;
+                }
+
+                let uti = self.mime_to_type(mime);
+                let data = NSData::with_bytes(data);
+                written |= item.setData_forType(&data, &NSString::from_str(&uti));
+            }
+
+            if !written {
+                return Err(CLIPBOARD_ERR_FORMAT_NOT_FOUND);
+            }
+            if formats.iter().any(|(_, _, is_sensitive)| *is_sensitive) {
+                item.setString_forType(&NSString::new(), &NSString::from_str(CONCEALED_TYPE));
+            }
+
+            items.insert(0, item);
+            let objects: Vec<Retained<ProtocolObject<dyn NSPasteboardWriting>>> =
+                items.into_iter().map(ProtocolObject::from_retained).collect();
+
+            self.pasteboard.clearContents();
+            if self.pasteboard.writeObjects(&NSArray::from_retained_slice(&objects)) {
+                Ok(())
+            } else {
+                Err(CLIPBOARD_ERR_INTERNAL)
+            }
+        })
+    }
+
+    // =========================================================================
+    // Internal helpers
+    // =========================================================================
+
+    fn read(&self, mime: &str) -> Result<Vec<u8>, i32> {
+        let items = self.pasteboard.pasteboardItems().ok_or(CLIPBOARD_ERR_EMPTY)?;
+        if items.is_empty() {
+            return Err(CLIPBOARD_ERR_EMPTY);
+        }
+
+        if mime == "text/uri-list" {
+            // Each file is its own item
+            let file_url = NSString::from_str("public.file-url");
+            let uris: String = items
+                .iter()
+                .filter_map(|item| item.stringForType(&file_url))
+                .map(|url| url.to_string() + "\r\n")
+                .collect();
+            return if uris.is_empty() { Err(CLIPBOARD_ERR_FORMAT_NOT_FOUND) } else { Ok(uris.into_bytes()) };
+        }
+
+        let uti = NSString::from_str(&crate::mime_to_pasteboard_type(mime));
+        items
+            .iter()
+            .find_map(|item| item.dataForType(&uti))
+            .map(|data| data.to_vec())
+            .ok_or(CLIPBOARD_ERR_FORMAT_NOT_FOUND)
+    }
+
+    fn mime_to_type(&mut self, mime: &str) -> String {
+        let uti = crate::mime_to_pasteboard_type(mime);
+        if uti.starts_with(crate::PASTEBOARD_MIME_PREFIX) {
+            self.custom_types.insert(uti.clone(), mime.to_string());
+        }
+        uti
+    }
+
+    fn type_to_mime(&self, uti: &str) -> Option<String> {
+        self.custom_types.get(uti).cloned().or_else(|| crate::pasteboard_type_to_mime(uti))
+    }
+}
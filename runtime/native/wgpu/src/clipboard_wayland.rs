@@ -120,6 +120,7 @@ impl WaylandClipboardBackend {
                             data,
                             formats: None,
                             format_cstrings: Vec::new(),
+                            image_size: None,
                             completed_at: Instant::now(),
                         },
                     );
@@ -177,6 +178,7 @@ impl WaylandClipboardBackend {
                         data: Vec::new(),
                         formats: Some(formats),
                         format_cstrings: Vec::new(),
+                        image_size: None,
                         completed_at: Instant::now(),
                     },
                 );
@@ -192,6 +194,7 @@ impl WaylandClipboardBackend {
                         data: Vec::new(),
                         formats: Some(vec![]),
                         format_cstrings: Vec::new(),
+                        image_size: None,
                         completed_at: Instant::now(),
                     },
                 );
@@ -302,12 +305,6 @@ impl WaylandClipboardBackend {
     ) {
         // smithay-clipboard operations are synchronous, so no timeouts to check
     }
-
-    /// Reset state (for testing)
-    #[cfg(test)]
-    pub fn reset(&mut self) {
-        self.write_data = None;
-    }
 }
 
 // =============================================================================
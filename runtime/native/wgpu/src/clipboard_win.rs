@@ -0,0 +1,534 @@
+//! Windows Clipboard Backend
+//!
+//! Native Win32 clipboard implementation using windows-sys.
+//! Enabled via `win-backend` feature flag on Windows.
+//!
+//! # Formats
+//!
+//! MIME types map to the formats other Windows applications expect:
+//! CF_UNICODETEXT, "HTML Format" (CF_HTML), "PNG" plus CF_DIB, and CF_HDROP.
+//! Any other MIME type is registered by name with RegisterClipboardFormatW,
+//! so custom formats round-trip byte for byte instead of degrading to text.
+//!
+//! # Delayed Rendering
+//!
+//! A write announces its formats with SetClipboardData(format, NULL) and keeps
+//! the data here. Windows sends WM_RENDERFORMAT to the hidden owner window the
+//! first time an application asks for a format, and WM_RENDERALLFORMATS before
+//! the window goes away, so payloads are only copied into global memory (and
+//! CF_DIB only converted from PNG) when someone actually pastes them.
+//!
+//! Window messages are delivered on the thread that created the backend,
+//! which is the thread that first used the clipboard; use it from the UI
+//! thread so other applications' paste requests are answered promptly.
+//!
+//! # Reads
+//!
+//! OpenClipboard fails while another application holds the clipboard, so reads
+//! are queued as pending operations and serviced from process_events(),
+//! retrying on each poll until the operation times out.
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::sync::Arc;
+use std::time::Instant;
+
+use windows_sys::Win32::Foundation::{GlobalFree, HWND, LPARAM, LRESULT, WPARAM};
+use windows_sys::Win32::System::DataExchange::{
+    CloseClipboard, EmptyClipboard, EnumClipboardFormats, GetClipboardData, GetClipboardFormatNameW,
+    GetClipboardOwner, OpenClipboard, RegisterClipboardFormatW, SetClipboardData,
+};
+use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows_sys::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalSize, GlobalUnlock, GMEM_MOVEABLE};
+use windows_sys::Win32::System::Ole::{CF_DIB, CF_DIBV5, CF_HDROP, CF_OEMTEXT, CF_TEXT, CF_UNICODETEXT};
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, PeekMessageW, RegisterClassExW,
+    HWND_MESSAGE, MSG, PM_REMOVE, WM_DESTROYCLIPBOARD, WM_RENDERALLFORMATS, WM_RENDERFORMAT, WNDCLASSEXW,
+};
+
+use crate::{
+    ClipboardCompletedData, NativeEvent, PendingOpState, PendingOperation, CLIPBOARD_ERR_ACCESS_DENIED,
+    CLIPBOARD_ERR_EMPTY, CLIPBOARD_ERR_FORMAT_NOT_FOUND, CLIPBOARD_ERR_INTERNAL,
+};
+
+// =============================================================================
+// Types
+// =============================================================================
+
+/// Data behind a delayed-rendered format
+enum RenderPayload {
+    /// Bytes placed on the clipboard as they are
+    Bytes(Vec<u8>),
+    /// CF_DIB converted from PNG data on first request
+    DibFromPng(Arc<Vec<u8>>),
+}
+
+/// Formats announced by our last write, rendered on request.
+/// Kept outside CLIPBOARD: WM_RENDERFORMAT can arrive while a read on this
+/// thread holds that lock (GetClipboardData on a format we own).
+static RENDER_DATA: Lazy<Mutex<Vec<(u32, RenderPayload)>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Kind of queued read
+enum WinReadKind {
+    /// Enumerate the available formats
+    Formats,
+    /// Read one MIME type
+    Data(String),
+}
+
+/// A read waiting for the clipboard to be free
+struct WinReadRequest {
+    callback_id: u64,
+    kind: WinReadKind,
+}
+
+/// Registered format IDs (constant for the session once registered)
+struct RegisteredFormats {
+    html: u32,
+    png: u32,
+    exclude_monitoring: u32,
+    include_in_history: u32,
+    upload_to_cloud: u32,
+}
+
+// =============================================================================
+// WinClipboardBackend
+// =============================================================================
+
+/// Native Windows clipboard backend
+pub struct WinClipboardBackend {
+    /// Hidden message-only window that owns the clipboard and renders formats
+    hwnd: HWND,
+    formats: RegisteredFormats,
+    pending_reads: Vec<WinReadRequest>,
+}
+
+// The HWND is only used through the clipboard API, which accepts it from any thread
+unsafe impl Send for WinClipboardBackend {}
+
+impl WinClipboardBackend {
+    /// Create the backend and its message-only owner window
+    pub fn new() -> Result<Self, String> {
+        let class_name = wide("QliphothClipboardOwner");
+
+        unsafe {
+            let instance = GetModuleHandleW(std::ptr::null());
+            let mut class: WNDCLASSEXW = std::mem::zeroed();
+            class.cbSize = std::mem::size_of::<WNDCLASSEXW>() as u32;
+            class.lpfnWndProc = Some(clipboard_wndproc);
+            class.hInstance = instance;
+            class.lpszClassName = class_name.as_ptr();
+            // Fails harmlessly if a previous backend already registered the class
+            RegisterClassExW(&class);
+
+            let hwnd = CreateWindowExW(
+                0,
+                class_name.as_ptr(),
+                class_name.as_ptr(),
+                0,
+                0,
+                0,
+                0,
+                0,
+                HWND_MESSAGE,
+                std::ptr::null_mut(),
+                instance,
+                std::ptr::null(),
+            );
+            if hwnd.is_null() {
+                return Err("Failed to create clipboard owner window".to_string());
+            }
+
+            log::debug!("Windows clipboard backend initialized");
+
+            Ok(Self {
+                hwnd,
+                formats: RegisteredFormats {
+                    html: register_format("HTML Format"),
+                    png: register_format("PNG"),
+                    exclude_monitoring: register_format("ExcludeClipboardContentFromMonitorProcessing"),
+                    include_in_history: register_format("CanIncludeInClipboardHistory"),
+                    upload_to_cloud: register_format("CanUploadToCloudClipboard"),
+                },
+                pending_reads: Vec::new(),
+            })
+        }
+    }
+
+    /// Queue a read of one MIME type
+    ///
+    /// The result is delivered from process_events() via
+    /// NativeEvent::ClipboardDataReady or NativeEvent::ClipboardError
+    pub fn read_format(&mut self, mime: &str, callback_id: u64) -> Result<(), i32> {
+        self.queue_read(callback_id, WinReadKind::Data(mime.to_string()))
+    }
+
+    /// Queue a query of the available formats
+    pub fn get_formats(&mut self, callback_id: u64) -> Result<(), i32> {
+        self.queue_read(callback_id, WinReadKind::Formats)
+    }
+
+    fn queue_read(&mut self, callback_id: u64, kind: WinReadKind) -> Result<(), i32> {
+        // Reject duplicate callback_id
+        if self.pending_reads.iter().any(|r| r.callback_id == callback_id) {
+            return Err(CLIPBOARD_ERR_INTERNAL);
+        }
+        self.pending_reads.push(WinReadRequest { callback_id, kind });
+        Ok(())
+    }
+
+    /// Take clipboard ownership and announce every format for delayed rendering.
+    /// Sensitive writes also set the formats that keep content out of clipboard
+    /// history, cloud sync and monitoring tools.
+    pub fn write(&mut self, formats: &[(String, Vec<u8>, bool)]) -> Result<(), i32> {
+        let mut payloads = Vec::new();
+        for (mime, data, _) in formats {
+            payloads.extend(self.encode_format(mime, data));
+        }
+        if payloads.is_empty() {
+            return Err(CLIPBOARD_ERR_FORMAT_NOT_FOUND);
+        }
+        let sensitive = formats.iter().any(|(_, _, is_sensitive)| *is_sensitive);
+
+        unsafe {
+            if OpenClipboard(self.hwnd) == 0 {
+                return Err(CLIPBOARD_ERR_ACCESS_DENIED);
+            }
+            // Sends WM_DESTROYCLIPBOARD to the previous owner, possibly us, so
+            // the new data is only stored afterwards
+            EmptyClipboard();
+
+            let announced: Vec<u32> = payloads.iter().map(|(format, _)| *format).collect();
+            *RENDER_DATA.lock() = payloads;
+            for format in announced {
+                SetClipboardData(format, std::ptr::null_mut());
+            }
+
+            if sensitive {
+                set_global_data(self.formats.exclude_monitoring, &[]);
+                set_global_data(self.formats.include_in_history, &0u32.to_le_bytes());
+                set_global_data(self.formats.upload_to_cloud, &0u32.to_le_bytes());
+            }
+
+            CloseClipboard();
+        }
+        Ok(())
+    }
+
+    /// Answer paste requests from other applications and service queued reads.
+    ///
+    /// This should be called from native_poll_event() to integrate Windows
+    /// clipboard events with the main event loop.
+    pub fn process_events(
+        &mut self,
+        event_queue: &mut Vec<NativeEvent>,
+        completed: &mut HashMap<u64, ClipboardCompletedData>,
+        pending_ops: &mut HashMap<u64, PendingOperation>,
+    ) {
+        // Deliver WM_RENDERFORMAT and friends (a no-op under a winit loop,
+        // which already dispatches them)
+        unsafe {
+            let mut msg: MSG = std::mem::zeroed();
+            while PeekMessageW(&mut msg, self.hwnd, 0, 0, PM_REMOVE) != 0 {
+                DispatchMessageW(&msg);
+            }
+        }
+
+        if self.pending_reads.is_empty() {
+            return;
+        }
+
+        // Another application holds the clipboard: retry on the next poll
+        if unsafe { OpenClipboard(self.hwnd) } == 0 {
+            return;
+        }
+
+        for request in std::mem::take(&mut self.pending_reads) {
+            // Cancelled or timed out meanwhile
+            let Some(op) = pending_ops.get_mut(&request.callback_id) else {
+                continue;
+            };
+            op.state = PendingOpState::Completed;
+            let callback_id = request.callback_id;
+
+            match request.kind {
+                WinReadKind::Formats => {
+                    let formats = self.available_formats();
+                    let format_count = formats.len();
+                    completed.insert(
+                        callback_id,
+                        ClipboardCompletedData {
+                            data: Vec::new(),
+                            formats: Some(formats),
+                            format_cstrings: Vec::new(),
+                            image_size: None,
+                            completed_at: Instant::now(),
+                        },
+                    );
+                    event_queue.push(NativeEvent::ClipboardFormatsAvailable { callback_id, format_count });
+                }
+                WinReadKind::Data(mime) => match self.read_open(&mime) {
+                    Ok(data) => {
+                        let data_size = data.len();
+                        completed.insert(
+                            callback_id,
+                            ClipboardCompletedData {
+                                data,
+                                formats: None,
+                                format_cstrings: Vec::new(),
+                                image_size: None,
+                                completed_at: Instant::now(),
+                            },
+                        );
+                        event_queue.push(NativeEvent::ClipboardDataReady { callback_id, data_size });
+                    }
+                    Err(error_code) => {
+                        event_queue.push(NativeEvent::ClipboardError { callback_id, error_code });
+                    }
+                },
+            }
+        }
+
+        unsafe {
+            CloseClipboard();
+        }
+    }
+
+    /// Cancel a queued read
+    pub fn cancel(&mut self, callback_id: u64) -> bool {
+        let before = self.pending_reads.len();
+        self.pending_reads.retain(|r| r.callback_id != callback_id);
+        self.pending_reads.len() != before
+    }
+
+    // =========================================================================
+    // Internal helpers
+    // =========================================================================
+
+    /// Clipboard formats (and their data) that represent one MIME type
+    fn encode_format(&self, mime: &str, data: &[u8]) -> Vec<(u32, RenderPayload)> {
+        match mime {
+            "text/plain" | "text/plain;charset=utf-8" => {
+                let text = String::from_utf8_lossy(data);
+                let units: Vec<u16> = text.encode_utf16().chain([0]).collect();
+                vec![(CF_UNICODETEXT as u32, RenderPayload::Bytes(units_to_bytes(&units)))]
+            }
+            "text/html" => {
+                let html = crate::cf_html_encode(&String::from_utf8_lossy(data));
+                vec![(self.formats.html, RenderPayload::Bytes(html))]
+            }
+            "image/png" => {
+                let png = Arc::new(data.to_vec());
+                vec![
+                    (self.formats.png, RenderPayload::Bytes(data.to_vec())),
+                    (CF_DIB as u32, RenderPayload::DibFromPng(png)),
+                ]
+            }
+            "text/uri-list" => {
+                let paths: Vec<Vec<u16>> = crate::uri_list_to_paths(&String::from_utf8_lossy(data))
+                    .iter()
+                    .map(|path| path.as_os_str().encode_wide().collect())
+                    .collect();
+                if paths.is_empty() {
+                    return Vec::new();
+                }
+                vec![(CF_HDROP as u32, RenderPayload::Bytes(crate::hdrop_encode(&paths)))]
+            }
+            // Custom formats are registered under their MIME type
+            _ => vec![(register_format(mime), RenderPayload::Bytes(data.to_vec()))],
+        }
+    }
+
+    /// MIME types available on the (open) clipboard
+    fn available_formats(&self) -> Vec<String> {
+        let mut formats = HashSet::new();
+        let mut format = 0;
+        loop {
+            format = unsafe { EnumClipboardFormats(format) };
+            if format == 0 {
+                break;
+            }
+            let mime = match format {
+                f if f == CF_UNICODETEXT as u32 || f == CF_TEXT as u32 || f == CF_OEMTEXT as u32 => {
+                    Some("text/plain".to_string())
+                }
+                f if f == CF_HDROP as u32 => Some("text/uri-list".to_string()),
+                f if f == CF_DIB as u32 || f == CF_DIBV5 as u32 || f == self.formats.png => {
+                    Some("image/png".to_string())
+                }
+                f if f == self.formats.html => Some("text/html".to_string()),
+                // Registered formats named like MIME types are custom formats
+                f => format_name(f).filter(|name| name.contains('/')),
+            };
+            formats.extend(mime);
+        }
+
+        // Sort for deterministic output order
+        let mut formats: Vec<String> = formats.into_iter().collect();
+        formats.sort();
+        formats
+    }
+
+    /// Read one MIME type from the (open) clipboard
+    fn read_open(&self, mime: &str) -> Result<Vec<u8>, i32> {
+        match mime {
+            "text/plain" | "text/plain;charset=utf-8" => {
+                let data = global_data(CF_UNICODETEXT as u32).ok_or(CLIPBOARD_ERR_EMPTY)?;
+                let units: Vec<u16> = data
+                    .chunks_exact(2)
+                    .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                    .take_while(|&u| u != 0)
+                    .collect();
+                Ok(String::from_utf16_lossy(&units).into_bytes())
+            }
+            "text/html" => {
+                let data = global_data(self.formats.html).ok_or(CLIPBOARD_ERR_EMPTY)?;
+                crate::cf_html_decode(&data).map(String::into_bytes).ok_or(CLIPBOARD_ERR_INTERNAL)
+            }
+            "image/png" => {
+                if let Some(png) = global_data(self.formats.png) {
+                    return Ok(png);
+                }
+                let dib = global_data(CF_DIBV5 as u32)
+                    .or_else(|| global_data(CF_DIB as u32))
+                    .ok_or(CLIPBOARD_ERR_EMPTY)?;
+                let (rgba, width, height) = crate::dib_to_rgba(&dib).ok_or(CLIPBOARD_ERR_INTERNAL)?;
+                crate::encode_rgba_to_png(&rgba, width, height).map_err(|_| CLIPBOARD_ERR_INTERNAL)
+            }
+            "text/uri-list" => {
+                let data = global_data(CF_HDROP as u32).ok_or(CLIPBOARD_ERR_EMPTY)?;
+                let paths: Vec<std::path::PathBuf> = crate::hdrop_decode(&data)
+                    .into_iter()
+                    .map(|units| OsString::from_wide(&units).into())
+                    .collect();
+                Ok(crate::paths_to_uri_list(&paths).into_bytes())
+            }
+            _ => global_data(register_format(mime)).ok_or(CLIPBOARD_ERR_FORMAT_NOT_FOUND),
+        }
+    }
+}
+
+impl Drop for WinClipboardBackend {
+    fn drop(&mut self) {
+        // Windows sends WM_RENDERALLFORMATS first if we still own the clipboard
+        unsafe {
+            DestroyWindow(self.hwnd);
+        }
+    }
+}
+
+// =============================================================================
+// Window procedure and Win32 helpers
+// =============================================================================
+
+unsafe extern "system" fn clipboard_wndproc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match msg {
+        WM_RENDERFORMAT => {
+            // The clipboard is already open on behalf of the requesting application
+            render_format(wparam as u32);
+            0
+        }
+        WM_RENDERALLFORMATS => {
+            if OpenClipboard(hwnd) != 0 {
+                // Another application may have taken ownership meanwhile
+                if GetClipboardOwner() == hwnd {
+                    let formats: Vec<u32> = RENDER_DATA.lock().iter().map(|(format, _)| *format).collect();
+                    for format in formats {
+                        render_format(format);
+                    }
+                }
+                CloseClipboard();
+            }
+            0
+        }
+        WM_DESTROYCLIPBOARD => {
+            RENDER_DATA.lock().clear();
+            0
+        }
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+/// Place the data for a delayed-rendered format on the clipboard
+fn render_format(format: u32) {
+    let bytes = {
+        let data = RENDER_DATA.lock();
+        let Some((_, payload)) = data.iter().find(|(f, _)| *f == format) else {
+            return;
+        };
+        match payload {
+            RenderPayload::Bytes(bytes) => bytes.clone(),
+            RenderPayload::DibFromPng(png) => match crate::decode_png_to_rgba(png) {
+                Ok((rgba, width, height)) => crate::rgba_to_dib(&rgba, width, height),
+                Err(_) => return,
+            },
+        }
+    };
+    unsafe {
+        set_global_data(format, &bytes);
+    }
+}
+
+/// Copy bytes into global memory and hand them to the (open) clipboard
+unsafe fn set_global_data(format: u32, bytes: &[u8]) {
+    // Zero-sized global allocations can't be locked
+    let handle = GlobalAlloc(GMEM_MOVEABLE, bytes.len().max(1));
+    if handle.is_null() {
+        return;
+    }
+    let ptr = GlobalLock(handle) as *mut u8;
+    if ptr.is_null() {
+        GlobalFree(handle);
+        return;
+    }
+    std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len());
+    GlobalUnlock(handle);
+
+    // On success the clipboard owns the memory
+    if SetClipboardData(format, handle).is_null() {
+        GlobalFree(handle);
+    }
+}
+
+/// Copy a format's global memory off the (open) clipboard.
+/// GlobalSize may round the size up, so custom formats can gain trailing zeros.
+fn global_data(format: u32) -> Option<Vec<u8>> {
+    unsafe {
+        let handle = GetClipboardData(format);
+        if handle.is_null() {
+            return None;
+        }
+        let ptr = GlobalLock(handle) as *const u8;
+        if ptr.is_null() {
+            return None;
+        }
+        let data = std::slice::from_raw_parts(ptr, GlobalSize(handle)).to_vec();
+        GlobalUnlock(handle);
+        Some(data)
+    }
+}
+
+/// Registered format ID for a name (the same ID in every process)
+fn register_format(name: &str) -> u32 {
+    let name = wide(name);
+    unsafe { RegisterClipboardFormatW(name.as_ptr()) }
+}
+
+/// Name of a registered format (None for predefined formats)
+fn format_name(format: u32) -> Option<String> {
+    let mut buf = [0u16; 256];
+    let len = unsafe { GetClipboardFormatNameW(format, buf.as_mut_ptr(), buf.len() as i32) };
+    (len > 0).then(|| String::from_utf16_lossy(&buf[..len as usize]))
+}
+
+/// NUL-terminated UTF-16 for Win32 string parameters
+fn wide(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain([0]).collect()
+}
+
+fn units_to_bytes(units: &[u16]) -> Vec<u8> {
+    units.iter().flat_map(|u| u.to_le_bytes()).collect()
+}
@@ -315,6 +315,19 @@ impl X11ClipboardBackend {
         Ok(())
     }
 
+    /// Write a text/uri-list (already percent-encoded) to clipboard (staged until commit)
+    #[allow(dead_code)] // Called when FFI layer routes through X11 backend
+    pub fn write_uri_list(&mut self, uri_list: &str) -> Result<(), i32> {
+        let write_data = self.write_data.get_or_insert(X11WriteData {
+            text: None,
+            html: None,
+            image_png: None,
+            uri_list: None,
+        });
+        write_data.uri_list = Some(uri_list.to_string());
+        Ok(())
+    }
+
     /// Commit all pending writes by taking selection ownership
     #[allow(dead_code)] // Called when FFI layer routes through X11 backend
     pub fn write_commit(&mut self, _callback_id: u64) -> Result<(), i32> {
@@ -398,17 +411,6 @@ impl X11ClipboardBackend {
         removed_pending || removed_incr
     }
 
-    /// Reset internal state and drain pending X11 events
-    /// Used during test state reset to prevent event leakage between tests
-    #[cfg(test)]
-    pub fn reset(&mut self) {
-        self.pending_reads.clear();
-        self.active_incr = None;
-        self.write_data = None;
-        // Drain any pending X11 events from the connection
-        while self.conn.poll_for_event().ok().flatten().is_some() {}
-    }
-
     // =========================================================================
     // Internal helpers
     // =========================================================================
@@ -534,6 +536,7 @@ impl X11ClipboardBackend {
                         data: Vec::new(),
                         formats: Some(formats),
                         format_cstrings: Vec::new(),
+                        image_size: None,
                         completed_at: Instant::now(),
                     },
                 );
@@ -552,6 +555,7 @@ impl X11ClipboardBackend {
                         data,
                         formats: None,
                         format_cstrings: Vec::new(),
+                        image_size: None,
                         completed_at: Instant::now(),
                     },
                 );
@@ -610,6 +614,7 @@ impl X11ClipboardBackend {
                     data: transfer.partial_data,
                     formats: None,
                     format_cstrings: Vec::new(),
+                    image_size: None,
                     completed_at: Instant::now(),
                 },
             );
@@ -0,0 +1,217 @@
+//! Golden-Image Testing
+//!
+//! Compares rendered windows against reference PNGs, so a layout or
+//! rendering change shows up as an image diff rather than slipping past
+//! pixel probes like `native_has_pixels_matching`. Built for `cargo test`
+//! and with the `testing` feature.
+//!
+//! # Golden files
+//!
+//! `assert_golden` compares against the PNG at a path. A missing file, or
+//! any file while `QLIPHOTH_UPDATE_GOLDEN=1` is set, is written from the
+//! actual render instead, so new goldens are created by running the tests
+//! once and reviewing the images. On a mismatch the actual render is saved
+//! next to the golden as `<name>.actual.png`.
+//!
+//! # Comparison
+//!
+//! Two measures, both of which must pass:
+//! - per channel: how many pixels have a channel that differs by more than
+//!   the tolerance, which catches moved or recolored elements
+//! - SSIM (structural similarity) over 8x8 blocks of luma, which tolerates
+//!   antialiasing noise but not changed structure
+
+use std::path::{Path, PathBuf};
+
+use crate::{decode_png_to_rgba, encode_rgba_to_png, native_render_to_buffer, native_window_size};
+
+/// Environment variable that rewrites every golden from the actual render
+pub const UPDATE_GOLDEN_ENV: &str = "QLIPHOTH_UPDATE_GOLDEN";
+
+/// Side of the square blocks SSIM is computed over
+const SSIM_BLOCK: u32 = 8;
+
+/// An RGBA8 image, rows top to bottom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl Image {
+    /// Decode a PNG file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let (rgba, width, height) = decode_png_to_rgba(&data).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Self { width, height, rgba })
+    }
+
+    /// Encode as a PNG file, creating its directory if needed
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let data = encode_rgba_to_png(&self.rgba, self.width, self.height).map_err(|e| e.to_string())?;
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        }
+        std::fs::write(path, data).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+/// Render a window with the software renderer. None for an unknown window.
+pub fn render_window(window: usize) -> Option<Image> {
+    let (mut width, mut height) = (0, 0);
+    native_window_size(window, &mut width, &mut height);
+    if width <= 0 || height <= 0 {
+        return None;
+    }
+    let (width, height) = (width as u32, height as u32);
+    let mut rgba = vec![0u8; (width * height * 4) as usize];
+    (native_render_to_buffer(window, rgba.as_mut_ptr(), width, height) == 1).then_some(Image { width, height, rgba })
+}
+
+/// How far an image may stray from its golden
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// Largest difference in any channel that still counts as a match
+    pub channel: u8,
+    /// How many pixels may differ by more than `channel`
+    pub max_differing: usize,
+    /// Lowest acceptable structural similarity (1.0 = identical)
+    pub min_ssim: f64,
+}
+
+impl Tolerance {
+    /// Every pixel identical
+    pub const EXACT: Self = Self { channel: 0, max_differing: 0, min_ssim: 1.0 };
+}
+
+impl Default for Tolerance {
+    /// Allows rounding differences between platforms and text backends
+    fn default() -> Self {
+        Self { channel: 2, max_differing: 0, min_ssim: 0.99 }
+    }
+}
+
+/// How two images of the same size differ
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageDiff {
+    /// Largest difference in any channel of any pixel
+    pub max_channel_delta: u8,
+    /// Pixels with a channel differing by more than the tolerance's `channel`
+    pub differing_pixels: usize,
+    /// Mean SSIM over blocks of luma, 1.0 for identical images
+    pub ssim: f64,
+}
+
+impl ImageDiff {
+    pub fn within(&self, tolerance: &Tolerance) -> bool {
+        self.differing_pixels <= tolerance.max_differing && self.ssim >= tolerance.min_ssim
+    }
+}
+
+/// Compare `actual` against `expected`, counting pixels beyond
+/// `tolerance.channel`. None if their sizes differ.
+pub fn compare(expected: &Image, actual: &Image, tolerance: &Tolerance) -> Option<ImageDiff> {
+    if (expected.width, expected.height) != (actual.width, actual.height)
+        || expected.rgba.len() != actual.rgba.len()
+    {
+        return None;
+    }
+    let mut max_channel_delta = 0;
+    let mut differing_pixels = 0;
+    for (e, a) in expected.rgba.chunks_exact(4).zip(actual.rgba.chunks_exact(4)) {
+        let delta = e.iter().zip(a).map(|(e, a)| e.abs_diff(*a)).max().unwrap_or(0);
+        max_channel_delta = max_channel_delta.max(delta);
+        if delta > tolerance.channel {
+            differing_pixels += 1;
+        }
+    }
+    let ssim = ssim(&luma(expected), &luma(actual), expected.width, expected.height);
+    Some(ImageDiff { max_channel_delta, differing_pixels, ssim })
+}
+
+/// Compare `actual` with the golden PNG at `path`, panicking with the
+/// difference if it doesn't match within `tolerance`. Writes the golden
+/// instead if it is missing or UPDATE_GOLDEN_ENV is set.
+pub fn assert_golden(path: impl AsRef<Path>, actual: &Image, tolerance: Tolerance) {
+    let path = path.as_ref();
+    if !path.exists() || std::env::var(UPDATE_GOLDEN_ENV).is_ok_and(|v| v == "1") {
+        if let Err(e) = actual.save(path) {
+            panic!("writing golden {}: {}", path.display(), e);
+        }
+        return;
+    }
+    let expected = match Image::load(path) {
+        Ok(image) => image,
+        Err(e) => panic!("reading golden {}", e),
+    };
+    let failure = match compare(&expected, actual, &tolerance) {
+        None => format!(
+            "size {}x{} doesn't match golden {}x{}",
+            actual.width, actual.height, expected.width, expected.height
+        ),
+        Some(diff) if !diff.within(&tolerance) => format!("{:?} exceeds {:?}", diff, tolerance),
+        Some(_) => return,
+    };
+    let actual_path = actual_path(path);
+    let saved = match actual.save(&actual_path) {
+        Ok(()) => format!("actual render saved to {}", actual_path.display()),
+        Err(e) => format!("actual render not saved: {}", e),
+    };
+    panic!("{} differs from its golden: {} ({})", path.display(), failure, saved);
+}
+
+/// `dir/name.png` -> `dir/name.actual.png`
+fn actual_path(golden: &Path) -> PathBuf {
+    let stem = golden.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    golden.with_file_name(format!("{}.actual.png", stem))
+}
+
+/// Rec. 601 luma of each pixel, composited over black
+fn luma(image: &Image) -> Vec<f64> {
+    image
+        .rgba
+        .chunks_exact(4)
+        .map(|p| (0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64) * p[3] as f64 / 255.0)
+        .collect()
+}
+
+/// Mean SSIM of two luma planes over SSIM_BLOCK-sized blocks (smaller at
+/// the right and bottom edges)
+fn ssim(a: &[f64], b: &[f64], width: u32, height: u32) -> f64 {
+    // Stabilizing constants for an 8-bit dynamic range
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let mut total = 0.0;
+    let mut blocks = 0;
+    for by in (0..height).step_by(SSIM_BLOCK as usize) {
+        for bx in (0..width).step_by(SSIM_BLOCK as usize) {
+            let indices = || {
+                (by..(by + SSIM_BLOCK).min(height))
+                    .flat_map(move |y| (bx..(bx + SSIM_BLOCK).min(width)).map(move |x| (y * width + x) as usize))
+            };
+            let n = indices().count() as f64;
+            let mean_a = indices().map(|i| a[i]).sum::<f64>() / n;
+            let mean_b = indices().map(|i| b[i]).sum::<f64>() / n;
+            let (mut var_a, mut var_b, mut cov) = (0.0, 0.0, 0.0);
+            for i in indices() {
+                let (da, db) = (a[i] - mean_a, b[i] - mean_b);
+                var_a += da * da;
+                var_b += db * db;
+                cov += da * db;
+            }
+            let (var_a, var_b, cov) = (var_a / n, var_b / n, cov / n);
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * cov + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            blocks += 1;
+        }
+    }
+    if blocks == 0 {
+        1.0
+    } else {
+        total / blocks as f64
+    }
+}
@@ -0,0 +1,138 @@
+//! System Tray Backend
+//!
+//! Platform tray icons using tray-icon (Win32 notification area, macOS
+//! status bar, libappindicator on Linux). Enabled via the `tray` feature.
+//!
+//! # Threading
+//!
+//! Tray icons must be created and dropped on the thread running the event
+//! loop, so every function here is called on the UI thread (the FFI layer
+//! routes through `on_ui_thread`). The icons live in a thread-local.
+//!
+//! # Events
+//!
+//! tray-icon reports clicks and menu selections through global handlers,
+//! installed once, which queue EVENT_TRAY_CLICK / EVENT_TRAY_MENU_SELECT.
+//! On Windows and macOS the OS messages wake winit. GTK, used on Linux, has
+//! no hook into winit's loop, so `pump` runs pending GTK events and asks the
+//! loop to poll while icons exist.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Once;
+use std::time::Duration;
+use tray_icon::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tray_icon::{Icon, MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
+
+use crate::{
+    queue_event, tray_click_event, tray_menu_event, tray_menu_id, wake_event_waiters, TrayMenuItem,
+    MOUSE_LEFT, MOUSE_MIDDLE, MOUSE_RIGHT,
+};
+
+/// How often the event loop wakes to service GTK while tray icons exist
+pub const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+thread_local! {
+    /// Platform icons by tray handle
+    static ICONS: RefCell<HashMap<usize, TrayIcon>> = RefCell::new(HashMap::new());
+}
+
+static HANDLERS: Once = Once::new();
+
+/// Route tray-icon's global click and menu handlers into the event queue
+fn install_handlers() {
+    HANDLERS.call_once(|| {
+        TrayIconEvent::set_event_handler(Some(|event: TrayIconEvent| {
+            // Report a click once, when the button comes back up
+            if let TrayIconEvent::Click { id, button, button_state: MouseButtonState::Up, .. } = event {
+                let button = match button {
+                    MouseButton::Left => MOUSE_LEFT,
+                    MouseButton::Right => MOUSE_RIGHT,
+                    MouseButton::Middle => MOUSE_MIDDLE,
+                };
+                if let Some(event) = tray_click_event(&id.0, button) {
+                    queue_event(event);
+                    wake_event_waiters();
+                }
+            }
+        }));
+        MenuEvent::set_event_handler(Some(|event: MenuEvent| {
+            if let Some(event) = tray_menu_event(&event.id.0) {
+                queue_event(event);
+                wake_event_waiters();
+            }
+        }));
+    });
+}
+
+/// Show a tray icon for `tray`
+pub fn create(tray: usize, rgba: Vec<u8>, width: u32, height: u32, tooltip: &str) -> Result<(), String> {
+    #[cfg(target_os = "linux")]
+    if !gtk::is_initialized() {
+        gtk::init().map_err(|e| e.to_string())?;
+    }
+    install_handlers();
+
+    let icon = Icon::from_rgba(rgba, width, height).map_err(|e| e.to_string())?;
+    let mut builder = TrayIconBuilder::new().with_id(tray).with_icon(icon);
+    if !tooltip.is_empty() {
+        builder = builder.with_tooltip(tooltip);
+    }
+    let icon = builder.build().map_err(|e| e.to_string())?;
+    ICONS.with(|icons| icons.borrow_mut().insert(tray, icon));
+    Ok(())
+}
+
+/// Replace the context menu of `tray`; an empty list removes it
+pub fn set_menu(tray: usize, items: &[TrayMenuItem]) -> Result<(), String> {
+    let menu = if items.is_empty() {
+        None
+    } else {
+        let menu = Menu::new();
+        for item in items {
+            match item {
+                TrayMenuItem::Item { id, label, enabled } => {
+                    menu.append(&MenuItem::with_id(tray_menu_id(tray, *id), label, *enabled, None))
+                }
+                TrayMenuItem::Separator => menu.append(&PredefinedMenuItem::separator()),
+            }
+            .map_err(|e| e.to_string())?;
+        }
+        Some(Box::new(menu) as Box<dyn tray_icon::menu::ContextMenu>)
+    };
+
+    ICONS.with(|icons| match icons.borrow().get(&tray) {
+        Some(icon) => {
+            icon.set_menu(menu);
+            Ok(())
+        }
+        // Creation failed earlier and was already reported
+        None => Err(format!("no icon for tray {}", tray)),
+    })
+}
+
+/// Remove the icon of `tray`
+pub fn destroy(tray: usize) {
+    ICONS.with(|icons| icons.borrow_mut().remove(&tray));
+}
+
+/// Remove every icon (runtime shutdown)
+pub fn destroy_all() {
+    ICONS.with(|icons| icons.borrow_mut().clear());
+}
+
+/// Service the platform tray from the event loop. Returns true while the
+/// loop has to poll to keep tray events flowing.
+pub fn pump() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        if gtk::is_initialized() {
+            while gtk::events_pending() {
+                gtk::main_iteration_do(false);
+            }
+        }
+        ICONS.with(|icons| !icons.borrow().is_empty())
+    }
+    #[cfg(not(target_os = "linux"))]
+    false
+}